@@ -0,0 +1,596 @@
+//! Symphonia-backed decoding for `AudioPlayer`.
+//!
+//! Replaces rodio's built-in `Decoder`, which only reliably yields `i16`
+//! samples and misreports duration for several container formats. A
+//! [`SymphoniaSource`] probes the file, decodes in `f32`, and implements
+//! `rodio::Source` directly so it can be appended to a `Sink` like any other
+//! source while also exposing sample-accurate seeking.
+
+use crate::resampler::Resampler;
+use rodio::Source;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
+
+/// Wraps a `Read + Seek` byte source and XORs every byte against a repeating
+/// key before it reaches the decoder, keyed by absolute stream position so
+/// it keeps working across seeks. Used to descramble protected/packaged
+/// assets handed over as an in-memory buffer, mirroring lonelyradio's
+/// extensible-reader approach.
+struct XorReader<R> {
+    inner: R,
+    key: Vec<u8>,
+    pos: u64,
+}
+
+impl<R> XorReader<R> {
+    fn new(inner: R, key: Vec<u8>) -> Self {
+        Self { inner, key, pos: 0 }
+    }
+}
+
+impl<R: Read> Read for XorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if !self.key.is_empty() {
+            for (i, byte) in buf[..n].iter_mut().enumerate() {
+                let key_byte = self.key[((self.pos + i as u64) % self.key.len() as u64) as usize];
+                *byte ^= key_byte;
+            }
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for XorReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = self.inner.seek(pos)?;
+        self.pos = new_pos;
+        Ok(new_pos)
+    }
+}
+
+impl<R: Read + Seek + Send + Sync> MediaSource for XorReader<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Consecutive decode errors a [`SymphoniaSource`] tolerates before treating
+/// the stream as exhausted, rather than aborting playback on one bad frame.
+const MAX_DECODE_ERRORS: u32 = 3;
+
+/// Demux (but don't decode) every packet on `track_id` to total up their
+/// durations, for containers that don't report `n_frames` in their codec
+/// parameters. Rewinds the reader back to the start of the track afterwards
+/// so the caller can still decode from position zero.
+fn scan_total_frames(format: &mut dyn FormatReader, track_id: u32) -> Option<u64> {
+    let mut total = 0u64;
+    loop {
+        match format.next_packet() {
+            Ok(packet) => {
+                if packet.track_id() == track_id {
+                    total += packet.dur;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    let _ = format.seek(
+        SeekMode::Accurate,
+        SeekTo::Time {
+            time: Time::from(0.0),
+            track_id: Some(track_id),
+        },
+    );
+
+    if total > 0 {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum DecoderError {
+    Io(std::io::Error),
+    Symphonia(SymphoniaError),
+    NoSupportedTrack,
+}
+
+impl std::fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecoderError::Io(e) => write!(f, "io error: {}", e),
+            DecoderError::Symphonia(e) => write!(f, "symphonia error: {}", e),
+            DecoderError::NoSupportedTrack => write!(f, "no supported audio track found"),
+        }
+    }
+}
+
+impl std::error::Error for DecoderError {}
+
+/// A `rodio::Source` backed by a Symphonia format reader and decoder.
+pub struct SymphoniaSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: u16,
+    time_base: Option<TimeBase>,
+    total_frames: Option<u64>,
+    sample_buf: Option<SampleBuffer<f32>>,
+    buf_pos: usize,
+    consecutive_errors: u32,
+}
+
+impl SymphoniaSource {
+    /// Probe `path` and build a source ready for playback from the start.
+    pub fn from_path(path: &str) -> Result<Self, DecoderError> {
+        let file = File::open(path).map_err(DecoderError::Io)?;
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|s| s.to_owned());
+
+        Self::from_media_source(Box::new(file), ext.as_deref())
+    }
+
+    /// Probe an in-memory buffer and build a source ready for playback,
+    /// without writing it to a temp file. Used for audio fetched over the
+    /// network or decrypted in JS.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, DecoderError> {
+        Self::from_media_source(Box::new(Cursor::new(data)), None)
+    }
+
+    /// Like [`SymphoniaSource::from_bytes`], but XOR-descrambles the byte
+    /// stream against `key` before the decoder sees it.
+    pub fn from_bytes_xor(data: Vec<u8>, key: Vec<u8>) -> Result<Self, DecoderError> {
+        let reader = XorReader::new(Cursor::new(data), key);
+        Self::from_media_source(Box::new(reader), None)
+    }
+
+    fn from_media_source(
+        source: Box<dyn MediaSource>,
+        ext_hint: Option<&str>,
+    ) -> Result<Self, DecoderError> {
+        let mss = MediaSourceStream::new(source, Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = ext_hint {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(DecoderError::Symphonia)?;
+
+        Self::from_format_reader(probed.format)
+    }
+
+    fn from_format_reader(mut format: Box<dyn FormatReader>) -> Result<Self, DecoderError> {
+        let (track_id, time_base, sample_rate, channels, codec_params) = {
+            let track = format
+                .tracks()
+                .iter()
+                .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+                .ok_or(DecoderError::NoSupportedTrack)?;
+
+            let sample_rate = track.codec_params.sample_rate.unwrap_or(44_100);
+            let channels = track
+                .codec_params
+                .channels
+                .map(|c| c.count() as u16)
+                .unwrap_or(2);
+
+            (
+                track.id,
+                track.codec_params.time_base,
+                sample_rate,
+                channels,
+                track.codec_params.clone(),
+            )
+        };
+
+        // Containers like MP3/OGG often don't report `n_frames` up front.
+        // Rather than leave the duration permanently unknown, demux the
+        // whole stream once to total up packet durations, then rewind so
+        // playback still starts from position zero.
+        let total_frames = match codec_params.n_frames {
+            Some(n) => Some(n),
+            None => scan_total_frames(format.as_mut(), track_id),
+        };
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&codec_params, &DecoderOptions::default())
+            .map_err(DecoderError::Symphonia)?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            channels,
+            time_base,
+            total_frames,
+            sample_buf: None,
+            buf_pos: 0,
+            consecutive_errors: 0,
+        })
+    }
+
+    /// Total number of frames in the track, as reported by the container or,
+    /// failing that, counted by demuxing the stream at construction.
+    pub fn total_frames(&self) -> Option<u64> {
+        self.total_frames
+    }
+
+    pub fn time_base(&self) -> Option<TimeBase> {
+        self.time_base
+    }
+
+    /// Seek to `position_secs` using the format reader's native seek support,
+    /// then reset the decoder so the next sample resumes cleanly from there.
+    ///
+    /// Operates on this same `FormatReader`/`Decoder` in place — callers that
+    /// want a persistent, re-seekable source should keep a `SymphoniaSource`
+    /// around (e.g. behind a [`SharedSymphoniaSource`]) rather than
+    /// re-opening and re-probing the file on every seek.
+    pub fn seek(&mut self, position_secs: f64) -> Result<(), DecoderError> {
+        let seek_to = SeekTo::Time {
+            time: Time::from(position_secs),
+            track_id: Some(self.track_id),
+        };
+
+        self.format
+            .seek(SeekMode::Accurate, seek_to)
+            .map_err(DecoderError::Symphonia)?;
+
+        self.decoder.reset();
+        self.sample_buf = None;
+        self.buf_pos = 0;
+        self.consecutive_errors = 0;
+
+        Ok(())
+    }
+
+    fn decode_next_packet(&mut self) -> bool {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => return false,
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    self.consecutive_errors = 0;
+                    let spec = *decoded.spec();
+                    let duration = decoded.capacity() as u64;
+                    let mut buf = SampleBuffer::<f32>::new(duration, spec);
+                    buf.copy_interleaved_ref(decoded);
+                    self.sample_buf = Some(buf);
+                    self.buf_pos = 0;
+                    return true;
+                }
+                Err(SymphoniaError::DecodeError(_)) => {
+                    self.consecutive_errors += 1;
+                    if self.consecutive_errors > MAX_DECODE_ERRORS {
+                        return false;
+                    }
+                    continue;
+                }
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+impl Iterator for SymphoniaSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            if let Some(buf) = &self.sample_buf {
+                let samples = buf.samples();
+                if self.buf_pos < samples.len() {
+                    let sample = samples[self.buf_pos];
+                    self.buf_pos += 1;
+                    return Some(sample);
+                }
+            }
+
+            if !self.decode_next_packet() {
+                return None;
+            }
+        }
+    }
+}
+
+impl Source for SymphoniaSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.sample_buf
+            .as_ref()
+            .map(|b| b.samples().len() - self.buf_pos)
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.total_frames?;
+        Some(Duration::from_secs_f64(frames as f64 / self.sample_rate as f64))
+    }
+}
+
+/// A `rodio::Source` that shares a decoder with other owners of the same
+/// `Arc<Mutex<SymphoniaSource>>`, locking it for each pulled sample.
+///
+/// `AudioPlayer` keeps one of these Arcs alongside the playback chain so
+/// `seek` can seek the live `FormatReader`/`Decoder` in place instead of
+/// re-opening and re-probing the file.
+pub struct SharedSymphoniaSource {
+    inner: Arc<Mutex<SymphoniaSource>>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl SharedSymphoniaSource {
+    pub fn new(inner: Arc<Mutex<SymphoniaSource>>) -> Self {
+        let (channels, sample_rate) = {
+            let guard = inner.lock().unwrap();
+            (guard.channels(), guard.sample_rate())
+        };
+        Self {
+            inner,
+            channels,
+            sample_rate,
+        }
+    }
+}
+
+impl Iterator for SharedSymphoniaSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.inner.lock().unwrap().next()
+    }
+}
+
+impl Source for SharedSymphoniaSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.lock().unwrap().current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.lock().unwrap().total_duration()
+    }
+}
+
+/// Sum up `(rms, peak)` over an already-decoded stream of `-1.0..1.0` f32
+/// samples, used by [`scan_peak_rms_in_place`] below.
+fn scan_samples_peak_rms(samples: impl Iterator<Item = f32>) -> (f32, f32) {
+    let mut sum_sq = 0.0_f64;
+    let mut count = 0u64;
+    let mut peak = 0.0_f32;
+
+    for sample in samples {
+        sum_sq += (sample as f64) * (sample as f64);
+        peak = peak.max(sample.abs());
+        count += 1;
+    }
+
+    let rms = if count > 0 {
+        (sum_sq / count as f64).sqrt() as f32
+    } else {
+        0.0
+    };
+
+    (rms, peak)
+}
+
+/// Scan a decoder the caller already constructed (in place, via `&mut`) for
+/// its overall `(rms, peak)` sample amplitude, used to derive an
+/// auto-normalization gain. Lets a single decode pass double as both the
+/// auto-normalization scan and, after the caller seeks it back to the
+/// start, the playback source — instead of decoding the whole file twice.
+pub fn scan_peak_rms_in_place(source: &mut SymphoniaSource) -> (f32, f32) {
+    scan_samples_peak_rms(source)
+}
+
+/// A `rodio::Source` that plays an optional intro once, then loops a body
+/// source forever.
+///
+/// Unlike rodio's built-in `Repeat`, the loop body is decoded to memory once
+/// up front and replayed from there, rather than re-opened from disk at
+/// every loop boundary: re-opening would mean blocking file I/O and a full
+/// format re-probe on the audio thread, which is exactly the click/stutter
+/// this is meant to avoid. This lets it be combined with a distinct lead-in
+/// with no gap at the loop boundary. The intro is resampled/remixed to the
+/// loop body's format if the two don't already match, since they come from
+/// separate files with no guarantee of a shared sample rate or channel count.
+pub struct LoopingSource {
+    intro: Option<Box<dyn Source<Item = f32> + Send>>,
+    loop_samples: Arc<[f32]>,
+    loop_pos: usize,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl LoopingSource {
+    pub fn new(intro: Option<SymphoniaSource>, loop_path: String) -> Result<Self, DecoderError> {
+        let loop_source = SymphoniaSource::from_path(&loop_path)?;
+        let channels = loop_source.channels();
+        let sample_rate = loop_source.sample_rate();
+        let loop_samples: Arc<[f32]> = loop_source.collect::<Vec<f32>>().into();
+
+        // The intro is a distinct file and isn't guaranteed to share the
+        // loop body's sample rate or channel count; resample/remix it to
+        // match before it's spliced in, otherwise it plays back at the
+        // wrong speed and/or with the wrong channel layout.
+        let intro: Option<Box<dyn Source<Item = f32> + Send>> = intro.map(|source| {
+            if source.sample_rate() == sample_rate && source.channels() == channels {
+                Box::new(source) as Box<dyn Source<Item = f32> + Send>
+            } else {
+                Box::new(Resampler::new(source, sample_rate, channels)) as Box<dyn Source<Item = f32> + Send>
+            }
+        });
+
+        Ok(Self {
+            intro,
+            loop_samples,
+            loop_pos: 0,
+            channels,
+            sample_rate,
+        })
+    }
+}
+
+impl Iterator for LoopingSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(intro) = &mut self.intro {
+            if let Some(sample) = intro.next() {
+                return Some(sample);
+            }
+            self.intro = None;
+        }
+
+        // An empty (silent or corrupt-but-probeable) loop body has nothing
+        // to replay; end the source instead of spinning forever.
+        if self.loop_samples.is_empty() {
+            return None;
+        }
+
+        let sample = self.loop_samples[self.loop_pos];
+        self.loop_pos += 1;
+        if self.loop_pos >= self.loop_samples.len() {
+            self.loop_pos = 0;
+        }
+        Some(sample)
+    }
+}
+
+impl Source for LoopingSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        // The stream has no end, so there is no fixed current frame.
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        // A looping track plays forever; it has no total duration.
+        None
+    }
+}
+
+/// Wraps a `Source<Item = f32>` and increments a shared sample counter as
+/// samples are pulled into the sink. `AudioPlayer` reads this counter to
+/// derive playback position, instead of estimating it from wall-clock time:
+/// the counter only advances while the sink is actually consuming audio, so
+/// it naturally accounts for pauses and never drifts from a seek offset.
+pub struct PositionTrackingSource<S> {
+    inner: S,
+    channels: u16,
+    sample_rate: u32,
+    samples_played: Arc<AtomicU64>,
+}
+
+impl<S> PositionTrackingSource<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(inner: S, samples_played: Arc<AtomicU64>) -> Self {
+        let channels = inner.channels();
+        let sample_rate = inner.sample_rate();
+        Self {
+            inner,
+            channels,
+            sample_rate,
+            samples_played,
+        }
+    }
+}
+
+impl<S> Iterator for PositionTrackingSource<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next();
+        if sample.is_some() {
+            self.samples_played.fetch_add(1, Ordering::Relaxed);
+        }
+        sample
+    }
+}
+
+impl<S> Source for PositionTrackingSource<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
@@ -1,8 +1,19 @@
+mod decoder;
+mod mixer;
+mod resampler;
+mod volume;
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use decoder::{
+    scan_peak_rms_in_place, LoopingSource, PositionTrackingSource, SharedSymphoniaSource,
+    SymphoniaSource,
+};
+use mixer::{SoundHandle, SoundMixer};
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
-use std::fs::File;
-use std::io::BufReader;
+use resampler::Resampler;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -12,8 +23,24 @@ pub struct AudioPlayer {
     _stream: Arc<Mutex<Option<OutputStream>>>,
     stream_handle: Arc<Mutex<Option<OutputStreamHandle>>>,
     current_file: Arc<Mutex<Option<String>>>,
+    /// The live decoder behind the currently playing track, if seekable in
+    /// place. `seek` seeks this directly instead of re-opening and
+    /// re-probing the file; `None` while nothing seekable is loaded (e.g.
+    /// a looping track).
+    current_decoder: Arc<Mutex<Option<Arc<Mutex<SymphoniaSource>>>>>,
     duration: Arc<Mutex<Duration>>,
-    start_time: Arc<Mutex<Option<std::time::Instant>>>,
+    samples_played: Arc<AtomicU64>,
+    channels: Arc<Mutex<u16>>,
+    sample_rate: Arc<Mutex<u32>>,
+    total_frames: Arc<Mutex<u64>>,
+    is_looping: Arc<Mutex<bool>>,
+    mixer: SoundMixer,
+    api_volume: Arc<Mutex<f32>>,
+    gain_db: Arc<Mutex<f32>>,
+    auto_normalize: Arc<Mutex<bool>>,
+    auto_gain: Arc<Mutex<f32>>,
+    output_sample_rate: u32,
+    output_channels: u16,
 }
 
 #[napi(object)]
@@ -37,46 +64,205 @@ impl AudioPlayer {
             }
         };
 
+        // Used to resample/remix decoded audio to what the device actually
+        // accepts; fall back to CD-quality stereo if the host doesn't report
+        // a default config.
+        let default_config = cpal::default_host()
+            .default_output_device()
+            .and_then(|d| d.default_output_config().ok());
+        let output_sample_rate = default_config
+            .as_ref()
+            .map(|c| c.sample_rate().0)
+            .unwrap_or(44_100);
+        let output_channels = default_config.map(|c| c.channels()).unwrap_or(2);
+
         Ok(Self {
             sink: Arc::new(Mutex::new(None)),
             _stream: Arc::new(Mutex::new(Some(stream))),
             stream_handle: Arc::new(Mutex::new(Some(stream_handle))),
             current_file: Arc::new(Mutex::new(None)),
+            current_decoder: Arc::new(Mutex::new(None)),
             duration: Arc::new(Mutex::new(Duration::ZERO)),
-            start_time: Arc::new(Mutex::new(None)),
+            samples_played: Arc::new(AtomicU64::new(0)),
+            channels: Arc::new(Mutex::new(2)),
+            sample_rate: Arc::new(Mutex::new(44_100)),
+            total_frames: Arc::new(Mutex::new(0)),
+            is_looping: Arc::new(Mutex::new(false)),
+            mixer: SoundMixer::new(output_sample_rate, output_channels),
+            api_volume: Arc::new(Mutex::new(1.0)),
+            gain_db: Arc::new(Mutex::new(0.0)),
+            auto_normalize: Arc::new(Mutex::new(false)),
+            auto_gain: Arc::new(Mutex::new(1.0)),
+            output_sample_rate,
+            output_channels,
         })
     }
 
+    /// Recompute the linear gain actually sent to the sink from the current
+    /// API volume, manual dB gain, and (if enabled) auto-normalization gain,
+    /// and apply it if a track is loaded.
+    fn apply_volume(&self) {
+        let effective = self.effective_gain();
+        let sink = self.sink.lock().unwrap();
+        if let Some(ref s) = *sink {
+            s.set_volume(effective);
+        }
+    }
+
+    /// Convert a frame count measured at `source_rate` into one measured at
+    /// the output device's rate, since `samples_played` counts samples after
+    /// resampling.
+    fn frames_at_output_rate(&self, source_rate: u32, source_frames: u64) -> u64 {
+        if source_rate == 0 {
+            return 0;
+        }
+        (source_frames as f64 * self.output_sample_rate as f64 / source_rate as f64).round() as u64
+    }
+
+    fn effective_gain(&self) -> f32 {
+        let perceptual = volume::perceptual_volume(*self.api_volume.lock().unwrap());
+        let manual = volume::db_to_linear(*self.gain_db.lock().unwrap());
+        let auto = if *self.auto_normalize.lock().unwrap() {
+            *self.auto_gain.lock().unwrap()
+        } else {
+            1.0
+        };
+        perceptual * manual * auto
+    }
+
+    /// Play `loop_path` forever, optionally preceded once by `intro_path`,
+    /// with no gap or click at the loop boundary. Replaces whatever is on
+    /// the music transport, the same as `load_file`.
     #[napi]
-    pub fn load_file(&mut self, file_path: String) -> Result<f64> {
-        // Stop current playback
+    pub fn load_looping(&mut self, intro_path: Option<String>, loop_path: String) -> Result<()> {
         self.stop()?;
 
-        // Open file and decode to get duration
-        let file = File::open(&file_path).map_err(|e| {
+        let intro = match intro_path {
+            Some(path) => Some(SymphoniaSource::from_path(&path).map_err(|e| {
+                Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to decode intro {}: {}", path, e),
+                )
+            })?),
+            None => None,
+        };
+
+        let looping = LoopingSource::new(intro, loop_path.clone()).map_err(|e| {
             Error::new(
                 Status::GenericFailure,
-                format!("Failed to open file {}: {}", file_path, e),
+                format!("Failed to decode loop {}: {}", loop_path, e),
             )
         })?;
 
-        let source = Decoder::new(BufReader::new(file)).map_err(|e| {
+        let stream_handle_guard = self.stream_handle.lock().unwrap();
+        let stream_handle_ref = stream_handle_guard
+            .as_ref()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Stream handle not available"))?;
+
+        let sink = Sink::try_new(stream_handle_ref).map_err(|e| {
             Error::new(
                 Status::GenericFailure,
-                format!("Failed to decode audio file: {}", e),
+                format!("Failed to create sink for loop: {}", e),
             )
         })?;
 
-        // Get duration
-        let duration = source.total_duration().unwrap_or(Duration::ZERO);
+        self.samples_played.store(0, Ordering::Relaxed);
+        let resampled = Resampler::new(looping, self.output_sample_rate, self.output_channels);
+        let tracked = PositionTrackingSource::new(resampled, Arc::clone(&self.samples_played));
+        sink.append(tracked);
+
+        *self.sink.lock().unwrap() = Some(sink);
+        *self.current_file.lock().unwrap() = None;
+        // A looping track has no single seekable decoder (it splices an
+        // intro and an in-memory loop buffer), so there's nothing for
+        // `seek` to seek in place.
+        *self.current_decoder.lock().unwrap() = None;
+        // A looping track has no total duration (it never ends), so
+        // `duration` stays zero. `get_position` treats a zero duration as
+        // "unknown" rather than "already over" and reports elapsed time
+        // since start unclamped, instead of pinning to zero forever.
+        *self.duration.lock().unwrap() = Duration::ZERO;
+        *self.channels.lock().unwrap() = self.output_channels;
+        *self.sample_rate.lock().unwrap() = self.output_sample_rate;
+        *self.total_frames.lock().unwrap() = 0;
+        *self.is_looping.lock().unwrap() = true;
+        self.apply_volume();
+
+        Ok(())
+    }
+
+    /// Stop a track started with `load_looping`. Equivalent to `stop`, kept
+    /// as a distinct name so callers don't need to track which transport is
+    /// active.
+    #[napi]
+    pub fn stop_loop(&self) -> Result<()> {
+        self.stop()
+    }
+
+    /// Play `file_path` as an overlapping one-shot sound effect, independent
+    /// of the music transport (`load_file`/`play`/`pause`/`seek`). Returns a
+    /// handle usable with `stop_sound`/`set_sound_volume`.
+    #[napi]
+    pub fn play_sound(&self, file_path: String) -> Result<SoundHandle> {
+        let stream_handle_guard = self.stream_handle.lock().unwrap();
+        let stream_handle_ref = stream_handle_guard
+            .as_ref()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Stream handle not available"))?;
+
+        self.mixer
+            .play_sound(stream_handle_ref, &file_path)
+            .map_err(|e| {
+                Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to play sound {}: {}", file_path, e),
+                )
+            })
+    }
+
+    #[napi]
+    pub fn stop_sound(&self, handle: SoundHandle) -> Result<()> {
+        self.mixer.stop_sound(handle);
+        Ok(())
+    }
+
+    #[napi]
+    pub fn set_sound_volume(&self, handle: SoundHandle, volume: f64) -> Result<()> {
+        let applied = self.mixer.set_sound_volume(handle, volume as f32);
+        if applied {
+            Ok(())
+        } else {
+            Err(Error::new(
+                Status::GenericFailure,
+                "No active sound for that handle",
+            ))
+        }
+    }
+
+    #[napi]
+    pub fn load_file(&mut self, file_path: String) -> Result<f64> {
+        // Stop current playback
+        self.stop()?;
+
+        let mut source = SymphoniaSource::from_path(&file_path).map_err(|e| {
+            Error::new(
+                Status::GenericFailure,
+                format!("Failed to decode audio file {}: {}", file_path, e),
+            )
+        })?;
+
+        let duration = source
+            .total_duration()
+            .unwrap_or(Duration::ZERO);
         let duration_secs = duration.as_secs_f64();
+        let source_rate = source.sample_rate();
+        let total_frames = self.frames_at_output_rate(source_rate, source.total_frames().unwrap_or(0));
 
         // Create new sink - need to get reference to stream handle
         let stream_handle_guard = self
             .stream_handle
             .lock()
             .unwrap();
-        
+
         let stream_handle_ref = stream_handle_guard
             .as_ref()
             .ok_or_else(|| Error::new(Status::GenericFailure, "Stream handle not available"))?;
@@ -88,33 +274,124 @@ impl AudioPlayer {
             )
         })?;
 
-        // Recreate source for playback
-        let file = File::open(&file_path).map_err(|e| {
+        self.samples_played.store(0, Ordering::Relaxed);
+
+        // If auto-normalization is on, this is the one full decode pass the
+        // file needs: scan it in place for peak/RMS, then seek the same
+        // decoder back to the start for playback, rather than decoding the
+        // whole file a second time via a fresh scan.
+        if *self.auto_normalize.lock().unwrap() {
+            let (rms, peak) = scan_peak_rms_in_place(&mut source);
+            *self.auto_gain.lock().unwrap() = volume::normalization_gain(rms, peak);
+            source.seek(0.0).map_err(|e| {
+                Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to rewind {} after loudness scan: {}", file_path, e),
+                )
+            })?;
+        }
+
+        // Kept behind an Arc<Mutex<_>> so `seek` can later seek this same
+        // decoder in place instead of re-opening and re-probing the file.
+        let decoder = Arc::new(Mutex::new(source));
+        let shared = SharedSymphoniaSource::new(Arc::clone(&decoder));
+        let resampled = Resampler::new(shared, self.output_sample_rate, self.output_channels);
+        let tracked = PositionTrackingSource::new(resampled, Arc::clone(&self.samples_played));
+        sink.append(tracked);
+
+        // Store sink and file info
+        *self.sink.lock().unwrap() = Some(sink);
+        *self.current_decoder.lock().unwrap() = Some(decoder);
+        *self.current_file.lock().unwrap() = Some(file_path);
+        *self.duration.lock().unwrap() = duration;
+        *self.channels.lock().unwrap() = self.output_channels;
+        *self.sample_rate.lock().unwrap() = self.output_sample_rate;
+        *self.total_frames.lock().unwrap() = total_frames;
+        self.apply_volume();
+
+        Ok(duration_secs)
+    }
+
+    /// Like `load_file`, but decodes audio bytes handed over directly (e.g.
+    /// fetched over the network or decrypted in JS) instead of a filesystem
+    /// path. If `xor_key` is given, the byte stream is XOR-descrambled
+    /// against it before the decoder sees it.
+    #[napi]
+    pub fn load_buffer(&mut self, data: Buffer, xor_key: Option<Buffer>) -> Result<f64> {
+        self.stop()?;
+
+        let bytes: Vec<u8> = data.to_vec();
+        let key: Option<Vec<u8>> = xor_key.map(|k| k.to_vec());
+
+        let mut source = match key {
+            Some(k) => SymphoniaSource::from_bytes_xor(bytes, k),
+            None => SymphoniaSource::from_bytes(bytes),
+        }
+        .map_err(|e| {
             Error::new(
                 Status::GenericFailure,
-                format!("Failed to reopen file: {}", e),
+                format!("Failed to decode audio buffer: {}", e),
             )
         })?;
-        let source = Decoder::new(BufReader::new(file)).map_err(|e| {
+
+        let duration = source.total_duration().unwrap_or(Duration::ZERO);
+        let duration_secs = duration.as_secs_f64();
+        let source_rate = source.sample_rate();
+        let total_frames = self.frames_at_output_rate(source_rate, source.total_frames().unwrap_or(0));
+
+        let stream_handle_guard = self.stream_handle.lock().unwrap();
+        let stream_handle_ref = stream_handle_guard
+            .as_ref()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Stream handle not available"))?;
+
+        let sink = Sink::try_new(stream_handle_ref).map_err(|e| {
             Error::new(
                 Status::GenericFailure,
-                format!("Failed to decode audio file: {}", e),
+                format!("Failed to create sink: {}", e),
             )
         })?;
 
-        sink.append(source);
+        self.samples_played.store(0, Ordering::Relaxed);
+
+        // Same single-decode-pass approach as `load_file`.
+        if *self.auto_normalize.lock().unwrap() {
+            let (rms, peak) = scan_peak_rms_in_place(&mut source);
+            *self.auto_gain.lock().unwrap() = volume::normalization_gain(rms, peak);
+            source.seek(0.0).map_err(|e| {
+                Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to rewind buffer after loudness scan: {}", e),
+                )
+            })?;
+        }
+
+        let decoder = Arc::new(Mutex::new(source));
+        let shared = SharedSymphoniaSource::new(Arc::clone(&decoder));
+        let resampled = Resampler::new(shared, self.output_sample_rate, self.output_channels);
+        let tracked = PositionTrackingSource::new(resampled, Arc::clone(&self.samples_played));
+        sink.append(tracked);
 
-        // Store sink and file info
         *self.sink.lock().unwrap() = Some(sink);
-        *self.current_file.lock().unwrap() = Some(file_path);
+        *self.current_decoder.lock().unwrap() = Some(decoder);
+        *self.current_file.lock().unwrap() = None;
         *self.duration.lock().unwrap() = duration;
-        *self.start_time.lock().unwrap() = None;
+        *self.channels.lock().unwrap() = self.output_channels;
+        *self.sample_rate.lock().unwrap() = self.output_sample_rate;
+        *self.total_frames.lock().unwrap() = total_frames;
+        self.apply_volume();
 
         Ok(duration_secs)
     }
 
     /// Seek to a position (in seconds) within the current file.
-    /// This recreates the decoder and skips samples up to the target position.
+    ///
+    /// Seeks the same decoder instance that's already playing in place,
+    /// rather than re-opening and re-probing the file: for formats without a
+    /// reliable frame count (MP3/OGG/AAC) a fresh `SymphoniaSource` has to
+    /// demux the whole stream just to learn its duration before it can even
+    /// seek, turning every seek back into the O(n) scan this was meant to
+    /// avoid. Duration and total_frames are already known from load time and
+    /// don't change, so seeking never needs to rescan for them.
     #[napi]
     pub fn seek(&mut self, position_secs: f64) -> Result<()> {
         let position_secs = if position_secs.is_sign_negative() {
@@ -123,9 +400,9 @@ impl AudioPlayer {
             position_secs
         };
 
-        let maybe_path = self.current_file.lock().unwrap().clone();
-        let path = match maybe_path {
-            Some(p) => p,
+        let decoder = self.current_decoder.lock().unwrap().clone();
+        let decoder = match decoder {
+            Some(d) => d,
             None => {
                 return Err(Error::new(
                     Status::GenericFailure,
@@ -134,48 +411,19 @@ impl AudioPlayer {
             }
         };
 
-        // Stop any current playback.
-        self.stop()?;
+        let duration_secs = self.duration.lock().unwrap().as_secs_f64();
+        let target_secs = position_secs.min(duration_secs).max(0.0);
 
-        // Open decoder again.
-        let file = File::open(&path).map_err(|e| {
-            Error::new(
-                Status::GenericFailure,
-                format!("Failed to open file for seek {}: {}", path, e),
-            )
-        })?;
+        // Stop any current playback, then seek the shared decoder directly.
+        self.stop()?;
 
-        let mut source = Decoder::new(BufReader::new(file)).map_err(|e| {
+        decoder.lock().unwrap().seek(target_secs).map_err(|e| {
             Error::new(
                 Status::GenericFailure,
-                format!("Failed to decode audio file for seek: {}", e),
+                format!("Failed to seek in audio file: {}", e),
             )
         })?;
 
-        let duration = source.total_duration().unwrap_or(Duration::ZERO);
-        let duration_secs = duration.as_secs_f64();
-
-        let sample_rate = source.sample_rate() as u64;
-        let channels = source.channels() as u64;
-
-        // Clamp target to duration.
-        let target_secs = position_secs
-            .min(duration_secs)
-            .max(0.0);
-        let target_frames = (target_secs * sample_rate as f64).round() as u64;
-        let samples_to_skip = target_frames.saturating_mul(channels);
-
-        // Skip samples by decoding and discarding.
-        let mut skipped: u64 = 0;
-        while skipped < samples_to_skip {
-            match source.next() {
-                Some(_sample) => {
-                    skipped += 1;
-                }
-                None => break,
-            }
-        }
-
         // Create a new sink at this position.
         let stream_handle_guard = self.stream_handle.lock().unwrap();
         let stream_handle_ref = stream_handle_guard
@@ -189,14 +437,24 @@ impl AudioPlayer {
             )
         })?;
 
-        sink.append(source);
+        // Initialize the counter to the target frame, in output-rate terms
+        // (samples_played counts interleaved samples after resampling, so the
+        // frame is scaled by the output channel count), so get_position
+        // reflects the seek immediately, before any new samples are pulled
+        // into the sink.
+        let target_output_frames = (target_secs * self.output_sample_rate as f64).round() as u64;
+        self.samples_played.store(
+            target_output_frames.saturating_mul(self.output_channels as u64),
+            Ordering::Relaxed,
+        );
+
+        let shared = SharedSymphoniaSource::new(decoder);
+        let resampled = Resampler::new(shared, self.output_sample_rate, self.output_channels);
+        let tracked = PositionTrackingSource::new(resampled, Arc::clone(&self.samples_played));
+        sink.append(tracked);
 
         *self.sink.lock().unwrap() = Some(sink);
-        *self.duration.lock().unwrap() = duration;
-
-        // Start time offset so native get_position aligns if used.
-        *self.start_time.lock().unwrap() =
-            Some(std::time::Instant::now() - Duration::from_secs_f64(target_secs));
+        self.apply_volume();
 
         Ok(())
     }
@@ -206,7 +464,6 @@ impl AudioPlayer {
         let sink = self.sink.lock().unwrap();
         if let Some(ref s) = *sink {
             s.play();
-            *self.start_time.lock().unwrap() = Some(std::time::Instant::now());
             Ok(())
         } else {
             Err(Error::new(
@@ -220,8 +477,9 @@ impl AudioPlayer {
     pub fn pause(&self) -> Result<()> {
         let sink = self.sink.lock().unwrap();
         if let Some(ref s) = *sink {
+            // The sample counter driving get_position only advances while the
+            // sink pulls frames, so pausing the sink is enough to freeze it.
             s.pause();
-            // Note: We keep start_time so position can be calculated when resumed
             Ok(())
         } else {
             Err(Error::new(
@@ -237,23 +495,47 @@ impl AudioPlayer {
         if let Some(s) = sink.take() {
             s.stop();
         }
-        *self.start_time.lock().unwrap() = None;
+        self.samples_played.store(0, Ordering::Relaxed);
+        *self.is_looping.lock().unwrap() = false;
         Ok(())
     }
 
+    /// Set the API-facing volume (0..1). Internally this is shaped through
+    /// a perceptual curve, and combined with any gain from `set_gain_db` or
+    /// auto-normalization, before being applied to the sink.
     #[napi]
     pub fn set_volume(&self, volume: f64) -> Result<()> {
-        let volume = volume.max(0.0).min(1.0);
-        let sink = self.sink.lock().unwrap();
-        if let Some(ref s) = *sink {
-            s.set_volume(volume as f32);
-            Ok(())
-        } else {
-            Err(Error::new(
-                Status::GenericFailure,
-                "No audio file loaded",
-            ))
-        }
+        *self.api_volume.lock().unwrap() = (volume as f32).max(0.0).min(1.0);
+        self.apply_volume();
+        Ok(())
+    }
+
+    /// Apply a fixed gain in decibels on top of the API volume, e.g. a
+    /// ReplayGain track or album value.
+    #[napi]
+    pub fn set_gain_db(&self, db: f64) -> Result<()> {
+        *self.gain_db.lock().unwrap() = db as f32;
+        self.apply_volume();
+        Ok(())
+    }
+
+    /// Toggle `auto` loudness normalization. When enabled, the gain computed
+    /// from peak/RMS by the most recent `load_file`/`load_buffer` that ran
+    /// with normalization enabled is folded into the effective volume;
+    /// enabling it after a load does not scan the already loaded track.
+    /// When disabled, only the API volume and manual dB gain apply.
+    #[napi]
+    pub fn set_auto_normalize(&self, enabled: bool) -> Result<()> {
+        *self.auto_normalize.lock().unwrap() = enabled;
+        self.apply_volume();
+        Ok(())
+    }
+
+    /// The linear gain currently applied to the sink (perceptual volume x
+    /// manual dB gain x auto-normalization gain), for UI display.
+    #[napi]
+    pub fn get_applied_gain(&self) -> Result<f64> {
+        Ok(self.effective_gain() as f64)
     }
 
     #[napi]
@@ -262,23 +544,59 @@ impl AudioPlayer {
         Ok(dur.as_secs_f64())
     }
 
+    /// Path of the currently loaded file, or `None` for a buffer-loaded or
+    /// looping track (the latter has no single backing path).
+    #[napi]
+    pub fn get_current_file(&self) -> Result<Option<String>> {
+        Ok(self.current_file.lock().unwrap().clone())
+    }
+
     #[napi]
     pub fn get_position(&self) -> Result<f64> {
-        let start_time = self.start_time.lock().unwrap();
-        if let Some(start) = *start_time {
-            let elapsed = start.elapsed();
-            Ok(elapsed.as_secs_f64())
+        let channels = (*self.channels.lock().unwrap()).max(1) as u64;
+        let sample_rate = *self.sample_rate.lock().unwrap();
+        if sample_rate == 0 {
+            return Ok(0.0);
+        }
+
+        let samples_played = self.samples_played.load(Ordering::Relaxed);
+        let frames_played = samples_played / channels;
+        let position_secs = frames_played as f64 / sample_rate as f64;
+
+        // A duration of zero means it's unknown (or, for a looping track,
+        // nonexistent) rather than "already over" — only clamp when it's a
+        // real upper bound, so position keeps advancing instead of pinning
+        // to zero.
+        let duration_secs = self.duration.lock().unwrap().as_secs_f64();
+        if duration_secs > 0.0 {
+            Ok(position_secs.min(duration_secs))
         } else {
-            Ok(0.0)
+            Ok(position_secs)
+        }
+    }
+
+    /// Whether the loaded track has been fully played through, based on the
+    /// sample counter rather than sink state (which clears once drained).
+    #[napi]
+    pub fn is_finished(&self) -> Result<bool> {
+        let total_frames = *self.total_frames.lock().unwrap();
+        if total_frames == 0 {
+            return Ok(false);
         }
+
+        let channels = (*self.channels.lock().unwrap()).max(1) as u64;
+        let frames_played = self.samples_played.load(Ordering::Relaxed) / channels;
+        Ok(frames_played >= total_frames)
     }
 
     #[napi]
     pub fn is_playing(&self) -> Result<bool> {
         let sink = self.sink.lock().unwrap();
         if let Some(ref s) = *sink {
-            // Check if sink is not paused and has content
-            Ok(!s.is_paused() && s.len() > 0)
+            // A looping track never drains its sink, so it's playing as long
+            // as it isn't paused, regardless of `len()`.
+            let looping = *self.is_looping.lock().unwrap();
+            Ok(!s.is_paused() && (looping || s.len() > 0))
         } else {
             Ok(false)
         }
@@ -295,47 +613,30 @@ impl AudioPlayer {
     }
 }
 
-/// Generate a simple waveform: bucketed peak amplitudes in 0..1.
-#[napi]
-pub fn get_waveform(path: String, buckets: u32) -> Result<WaveformData> {
+/// Bucket a stream of (already -1.0..1.0 normalized) samples into `buckets`
+/// RMS peaks, with log scaling like OneTagger, shared by `get_waveform` and
+/// `get_waveform_from_buffer`.
+fn bucket_waveform(
+    samples: impl Iterator<Item = f32>,
+    channels: u64,
+    total_frames: u64,
+    duration_ms: f64,
+    buckets: u32,
+) -> WaveformData {
     let buckets = buckets.max(8).min(4096); // clamp for sanity
-
-    let file = File::open(&path).map_err(|e| {
-        Error::new(
-            Status::GenericFailure,
-            format!("Failed to open file for waveform {}: {}", path, e),
-        )
-    })?;
-
-    let mut source = Decoder::new(BufReader::new(file)).map_err(|e| {
-        Error::new(
-            Status::GenericFailure,
-            format!("Failed to decode audio file for waveform: {}", e),
-        )
-    })?;
-
-    let duration = source.total_duration().unwrap_or(Duration::ZERO);
-    let duration_secs = duration.as_secs_f64();
-    let duration_ms = duration_secs * 1000.0;
-
-    let sample_rate = source.sample_rate() as u64;
-    let channels = source.channels() as u64;
-    let total_frames =
-        (duration_secs.max(0.0) * sample_rate as f64).round().max(1.0) as u64;
+    let channels = channels.max(1);
+    let total_frames = total_frames.max(1);
 
     // Use RMS (root mean square) for smoother waveform, with log scaling like OneTagger
     let mut bucket_samples: Vec<Vec<f32>> = vec![Vec::new(); buckets as usize];
     let mut sample_index: u64 = 0;
-    
-    while let Some(sample) = source.next() {
-        let frame_index = sample_index / channels.max(1);
+
+    for value in samples {
+        let frame_index = sample_index / channels;
         let bucket_index = ((frame_index.saturating_mul(buckets as u64)) / total_frames)
             .min(buckets as u64 - 1);
 
-        // Decoder currently yields i16 samples; normalize to -1.0..1.0.
-        let value = sample as f32 / i16::MAX as f32;
         bucket_samples[bucket_index as usize].push(value);
-
         sample_index = sample_index.saturating_add(1);
     }
 
@@ -385,10 +686,67 @@ pub fn get_waveform(path: String, buckets: u32) -> Result<WaveformData> {
         }
     }
 
-    let peaks_f64: Vec<f64> = peaks.into_iter().map(|p| p as f64).collect();
-
-    Ok(WaveformData {
+    WaveformData {
         duration_ms,
-        peaks: peaks_f64,
-    })
+        peaks: peaks.into_iter().map(|p| p as f64).collect(),
+    }
+}
+
+/// Generate a simple waveform: bucketed peak amplitudes in 0..1.
+#[napi]
+pub fn get_waveform(path: String, buckets: u32) -> Result<WaveformData> {
+    let source = SymphoniaSource::from_path(&path).map_err(|e| {
+        Error::new(
+            Status::GenericFailure,
+            format!("Failed to decode audio file for waveform {}: {}", path, e),
+        )
+    })?;
+
+    let duration = source.total_duration().unwrap_or(Duration::ZERO);
+    let duration_secs = duration.as_secs_f64();
+    let duration_ms = duration_secs * 1000.0;
+    let channels = source.channels() as u64;
+    let sample_rate = source.sample_rate() as u64;
+    let total_frames = estimate_total_frames(source.total_frames(), duration_secs, sample_rate);
+
+    Ok(bucket_waveform(source, channels, total_frames, duration_ms, buckets))
+}
+
+/// Frame count to bucket a waveform over: the decoder's own count when it
+/// has one, otherwise a duration-derived estimate rather than `1`, which
+/// would dump almost every frame into the final bucket.
+fn estimate_total_frames(total_frames: Option<u64>, duration_secs: f64, sample_rate: u64) -> u64 {
+    total_frames
+        .unwrap_or_else(|| (duration_secs.max(0.0) * sample_rate as f64).round().max(1.0) as u64)
+        .max(1)
+}
+
+/// Like `get_waveform`, but over an in-memory buffer instead of a filesystem
+/// path, with the same optional XOR-descrambling as `load_buffer`.
+#[napi]
+pub fn get_waveform_from_buffer(
+    data: Buffer,
+    buckets: u32,
+    xor_key: Option<Buffer>,
+) -> Result<WaveformData> {
+    let bytes: Vec<u8> = data.to_vec();
+    let source = match xor_key {
+        Some(key) => SymphoniaSource::from_bytes_xor(bytes, key.to_vec()),
+        None => SymphoniaSource::from_bytes(bytes),
+    }
+    .map_err(|e| {
+        Error::new(
+            Status::GenericFailure,
+            format!("Failed to decode audio buffer for waveform: {}", e),
+        )
+    })?;
+
+    let duration = source.total_duration().unwrap_or(Duration::ZERO);
+    let duration_secs = duration.as_secs_f64();
+    let duration_ms = duration_secs * 1000.0;
+    let channels = source.channels() as u64;
+    let sample_rate = source.sample_rate() as u64;
+    let total_frames = estimate_total_frames(source.total_frames(), duration_secs, sample_rate);
+
+    Ok(bucket_waveform(source, channels, total_frames, duration_ms, buckets))
 }
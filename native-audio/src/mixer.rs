@@ -0,0 +1,108 @@
+//! Sound-effects mixer: a pool of concurrently-playing one-shot sinks.
+//!
+//! Modeled on Ruffle's `Arena<Sink>` active-sounds approach: each call to
+//! `play_sound` decodes the file into a fresh `Sink` and stores it in a
+//! generational arena, so a numeric handle handed to JS can't silently
+//! alias a different sound once the original has finished and been reaped.
+//! Each effect is resampled to the output device's native rate/channels the
+//! same way the music transport is, so both paths get consistent output.
+
+use crate::decoder::SymphoniaSource;
+use crate::resampler::Resampler;
+use generational_arena::{Arena, Index};
+use rodio::{OutputStreamHandle, Sink};
+use std::sync::{Arc, Mutex};
+
+/// Opaque handle to a sound playing through the mixer, exposed to JS as a
+/// plain number (packed arena index + generation).
+pub type SoundHandle = i64;
+
+fn handle_to_index(handle: SoundHandle) -> Index {
+    let raw = handle as u64;
+    let index = (raw & 0xFFFF_FFFF) as usize;
+    let generation = raw >> 32;
+    Index::from_raw_parts(index, generation)
+}
+
+fn index_to_handle(index: Index) -> SoundHandle {
+    let (idx, generation) = index.into_raw_parts();
+    ((generation << 32) | (idx as u64)) as SoundHandle
+}
+
+/// Keeps every currently-playing one-shot sound effect alive in its own
+/// `Sink`, independent of the single "music" transport on `AudioPlayer`.
+pub struct SoundMixer {
+    active: Arc<Mutex<Arena<Sink>>>,
+    output_sample_rate: u32,
+    output_channels: u16,
+}
+
+impl SoundMixer {
+    /// `output_sample_rate`/`output_channels` are the device's native format
+    /// (see `AudioPlayer::new`), so one-shot effects get the same resampling
+    /// and remixing as the music transport instead of relying on rodio's
+    /// internal conversion.
+    pub fn new(output_sample_rate: u32, output_channels: u16) -> Self {
+        Self {
+            active: Arc::new(Mutex::new(Arena::new())),
+            output_sample_rate,
+            output_channels,
+        }
+    }
+
+    /// Decode `file_path` into a new sink and start it playing immediately.
+    pub fn play_sound(
+        &self,
+        stream_handle: &OutputStreamHandle,
+        file_path: &str,
+    ) -> Result<SoundHandle, String> {
+        let source = SymphoniaSource::from_path(file_path).map_err(|e| e.to_string())?;
+        let resampled = Resampler::new(source, self.output_sample_rate, self.output_channels);
+        let sink = Sink::try_new(stream_handle).map_err(|e| e.to_string())?;
+        sink.append(resampled);
+
+        let mut active = self.active.lock().unwrap();
+        self.reap_locked(&mut active);
+        let index = active.insert(sink);
+        Ok(index_to_handle(index))
+    }
+
+    pub fn stop_sound(&self, handle: SoundHandle) {
+        let mut active = self.active.lock().unwrap();
+        if let Some(sink) = active.remove(handle_to_index(handle)) {
+            sink.stop();
+        }
+    }
+
+    /// Returns `false` if `handle` no longer points at a sound in the mixer
+    /// (stopped, or finished and reaped).
+    pub fn set_sound_volume(&self, handle: SoundHandle, volume: f32) -> bool {
+        let active = self.active.lock().unwrap();
+        match active.get(handle_to_index(handle)) {
+            Some(sink) => {
+                sink.set_volume(volume.max(0.0).min(1.0));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop sinks that have finished playing (`len() == 0`). Also run
+    /// opportunistically before each new `play_sound` so the arena doesn't
+    /// grow unbounded between explicit reaps.
+    pub fn reap(&self) {
+        let mut active = self.active.lock().unwrap();
+        self.reap_locked(&mut active);
+    }
+
+    fn reap_locked(&self, active: &mut Arena<Sink>) {
+        let finished: Vec<Index> = active
+            .iter()
+            .filter(|(_, sink)| sink.len() == 0)
+            .map(|(idx, _)| idx)
+            .collect();
+        for idx in finished {
+            active.remove(idx);
+        }
+    }
+}
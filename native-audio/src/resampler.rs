@@ -0,0 +1,231 @@
+//! Linear-interpolation resampling to the output device's native format.
+//!
+//! Appending a source straight to a `Sink` assumes the playback backend
+//! accepts the file's sample rate and channel layout; when the device
+//! differs this can pitch-shift or fail on some hosts. [`Resampler`] sits
+//! between the decoder and the sink and converts both, the same approach
+//! gonk-player's `Resampler` uses: keep a `current_frame`/`next_frame` pair
+//! and interpolate between them with [`lerp`], advancing through the input
+//! by a step ratio reduced via [`gcd`].
+
+use rodio::Source;
+use std::time::Duration;
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a.max(1)
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Remix an interpolated source-channel frame into `target_channels`: exact
+/// match copies through, downmix-to-mono averages, upmix cycles the source
+/// channels, and any other mismatch just drops the extra channels.
+fn remix(source: &[f32], target_channels: u16, out: &mut Vec<f32>) {
+    let target_channels = target_channels as usize;
+    out.clear();
+
+    if source.len() == target_channels {
+        out.extend_from_slice(source);
+    } else if target_channels == 1 {
+        out.push(source.iter().sum::<f32>() / source.len() as f32);
+    } else if target_channels > source.len() {
+        out.extend((0..target_channels).map(|i| source[i % source.len()]));
+    } else {
+        out.extend_from_slice(&source[..target_channels]);
+    }
+}
+
+/// A `rodio::Source` that resamples and remixes an inner source to a fixed
+/// output sample rate and channel count.
+pub struct Resampler<S> {
+    inner: S,
+    target_channels: u16,
+    target_rate: u32,
+    step: f64,
+    frame_pos: f64,
+    current_frame: Vec<f32>,
+    next_frame: Vec<f32>,
+    interpolated: Vec<f32>,
+    mixed_frame: Vec<f32>,
+    out_channel: usize,
+    exhausted: bool,
+}
+
+impl<S> Resampler<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(mut inner: S, target_rate: u32, target_channels: u16) -> Self {
+        let source_channels = inner.channels();
+        let source_rate = inner.sample_rate().max(1);
+        let target_rate = target_rate.max(1);
+
+        let g = gcd(source_rate, target_rate);
+        let step = (source_rate / g) as f64 / (target_rate / g) as f64;
+
+        let mut current_frame = vec![0.0; source_channels as usize];
+        let mut next_frame = vec![0.0; source_channels as usize];
+        let have_frames = Self::fill_frame(&mut inner, &mut current_frame)
+            && Self::fill_frame(&mut inner, &mut next_frame);
+
+        let interpolated = vec![0.0; source_channels as usize];
+
+        let mut resampler = Self {
+            inner,
+            target_channels,
+            target_rate,
+            step,
+            frame_pos: 0.0,
+            current_frame,
+            next_frame,
+            interpolated,
+            mixed_frame: Vec::with_capacity(target_channels as usize),
+            out_channel: 0,
+            exhausted: !have_frames,
+        };
+        resampler.remix_current();
+        resampler
+    }
+
+    fn fill_frame(inner: &mut S, frame: &mut [f32]) -> bool {
+        for slot in frame.iter_mut() {
+            match inner.next() {
+                Some(sample) => *slot = sample,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    fn remix_current(&mut self) {
+        let t = self.frame_pos as f32;
+        self.interpolated.clear();
+        self.interpolated.extend(
+            self.current_frame
+                .iter()
+                .zip(&self.next_frame)
+                .map(|(&a, &b)| lerp(a, b, t)),
+        );
+        remix(&self.interpolated, self.target_channels, &mut self.mixed_frame);
+    }
+}
+
+impl<S> Iterator for Resampler<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.exhausted && self.out_channel == 0 {
+            return None;
+        }
+
+        let value = self.mixed_frame[self.out_channel];
+        self.out_channel += 1;
+
+        if self.out_channel >= self.target_channels as usize {
+            self.out_channel = 0;
+            self.frame_pos += self.step;
+
+            while self.frame_pos >= 1.0 {
+                self.frame_pos -= 1.0;
+                self.current_frame.copy_from_slice(&self.next_frame);
+                if !Self::fill_frame(&mut self.inner, &mut self.next_frame) {
+                    self.exhausted = true;
+                    break;
+                }
+            }
+
+            self.remix_current();
+        }
+
+        Some(value)
+    }
+}
+
+impl<S> Source for Resampler<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.target_channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.target_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_reduces_common_factors() {
+        assert_eq!(gcd(48_000, 44_100), 300);
+        assert_eq!(gcd(44_100, 44_100), 44_100);
+    }
+
+    #[test]
+    fn gcd_handles_zero() {
+        assert_eq!(gcd(5, 0), 5);
+    }
+
+    #[test]
+    fn lerp_interpolates_between_endpoints() {
+        assert_eq!(lerp(0.0, 10.0, 0.0), 0.0);
+        assert_eq!(lerp(0.0, 10.0, 1.0), 10.0);
+        assert_eq!(lerp(0.0, 10.0, 0.5), 5.0);
+        assert_eq!(lerp(-1.0, 1.0, 0.25), -0.5);
+    }
+
+    #[test]
+    fn remix_passes_through_matching_channel_count() {
+        let mut out = Vec::new();
+        remix(&[0.1, 0.2], 2, &mut out);
+        assert_eq!(out, vec![0.1, 0.2]);
+    }
+
+    #[test]
+    fn remix_downmixes_to_mono_by_averaging() {
+        let mut out = Vec::new();
+        remix(&[1.0, 0.0, -1.0, 0.0], 1, &mut out);
+        assert_eq!(out, vec![0.0]);
+    }
+
+    #[test]
+    fn remix_upmixes_by_cycling_source_channels() {
+        let mut out = Vec::new();
+        remix(&[0.5, -0.5], 4, &mut out);
+        assert_eq!(out, vec![0.5, -0.5, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn remix_drops_extra_channels_on_mismatch() {
+        let mut out = Vec::new();
+        remix(&[0.1, 0.2, 0.3], 2, &mut out);
+        assert_eq!(out, vec![0.1, 0.2]);
+    }
+
+    #[test]
+    fn remix_reuses_the_output_buffer() {
+        let mut out = vec![9.0, 9.0, 9.0];
+        remix(&[0.4, 0.6], 2, &mut out);
+        assert_eq!(out, vec![0.4, 0.6]);
+    }
+}
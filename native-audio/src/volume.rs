@@ -0,0 +1,93 @@
+//! Perceptual volume mapping and loudness normalization helpers.
+//!
+//! Rodio's sink volume is a raw linear gain applied directly to samples,
+//! which doesn't match how loud a human perceives it: halving the linear
+//! gain doesn't sound half as loud. These helpers shape the API-facing
+//! volume and any additional gain (manual dB or auto-normalization) before
+//! it reaches the sink.
+
+/// Shapes a linear 0..1 API volume onto a perceptual curve, the same
+/// exponential approach gonk-player's `VOLUME_REDUCTION` uses.
+const VOLUME_REDUCTION: f32 = 6.0;
+
+/// Target RMS for `auto` normalization, roughly -18 dBFS, a common
+/// "album normalized" loudness target.
+const TARGET_RMS: f32 = 0.125;
+
+/// Map a 0..1 API volume onto the linear gain actually sent to the sink.
+pub fn perceptual_volume(volume: f32) -> f32 {
+    let volume = volume.max(0.0).min(1.0);
+    ((VOLUME_REDUCTION * volume).exp() - 1.0) / (VOLUME_REDUCTION.exp() - 1.0)
+}
+
+/// Convert a decibel gain (e.g. a ReplayGain track/album value) to the
+/// linear multiplier rodio's `Sink::set_volume` expects.
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Derive the linear gain that brings `rms` up to [`TARGET_RMS`] without
+/// pushing `peak` past 1.0 (clipping).
+pub fn normalization_gain(rms: f32, peak: f32) -> f32 {
+    if rms <= 0.0 {
+        return 1.0;
+    }
+
+    let rms_gain = TARGET_RMS / rms;
+    let max_gain_before_clip = if peak > 0.0 { 1.0 / peak } else { rms_gain };
+
+    rms_gain.min(max_gain_before_clip).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perceptual_volume_clamps_to_0_1() {
+        assert_eq!(perceptual_volume(-1.0), perceptual_volume(0.0));
+        assert_eq!(perceptual_volume(2.0), perceptual_volume(1.0));
+    }
+
+    #[test]
+    fn perceptual_volume_is_monotonic_and_bounded() {
+        assert_eq!(perceptual_volume(0.0), 0.0);
+        assert!((perceptual_volume(1.0) - 1.0).abs() < 1e-6);
+        assert!(perceptual_volume(0.5) < perceptual_volume(1.0));
+        assert!(perceptual_volume(0.25) < perceptual_volume(0.5));
+    }
+
+    #[test]
+    fn db_to_linear_unity_at_zero_db() {
+        assert!((db_to_linear(0.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn db_to_linear_halves_around_minus_6_db() {
+        assert!((db_to_linear(-6.0) - 0.5011872).abs() < 1e-4);
+    }
+
+    #[test]
+    fn normalization_gain_boosts_quiet_audio_to_target_rms() {
+        let gain = normalization_gain(0.0625, 0.25);
+        // TARGET_RMS / rms = 0.125 / 0.0625 = 2.0, and peak * gain = 0.5 <= 1.0.
+        assert!((gain - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalization_gain_caps_at_1_over_peak_to_avoid_clipping() {
+        // Without the clip cap, rms_gain alone would be 0.125 / 0.01 = 12.5,
+        // which would push a peak of 0.9 up to 11.25 and clip hard; the gain
+        // actually returned must never push the peak past 1.0.
+        let rms = 0.01;
+        let peak = 0.9;
+        let gain = normalization_gain(rms, peak);
+        assert!((gain - 1.0 / peak).abs() < 1e-6);
+        assert!(peak * gain <= 1.0 + 1e-6);
+    }
+
+    #[test]
+    fn normalization_gain_defaults_to_unity_for_silence() {
+        assert_eq!(normalization_gain(0.0, 0.0), 1.0);
+    }
+}